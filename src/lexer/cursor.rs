@@ -0,0 +1,104 @@
+use std::iter;
+use std::str;
+
+/// Character iterator over the source text that keeps track of the current
+/// line and byte offset. `\n`, `\r\n` and a lone `\r` each count as a single
+/// line break.
+pub struct Cursor<'a> {
+    source: &'a str,
+    chars: iter::Peekable<str::Chars<'a>>,
+    line: usize,
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(source: &'a str) -> Cursor<'a> {
+        Cursor {
+            source,
+            chars: source.chars().peekable(),
+            line: 1,
+            offset: 0,
+        }
+    }
+
+    pub fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    /// Looks `n` characters past the next one without consuming anything;
+    /// `peek_nth(0)` is the same character as `peek`.
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    /// The source text that has not been consumed yet.
+    pub fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let character = self.chars.next()?;
+        self.offset += character.len_utf8();
+        match character {
+            '\n' => self.line += 1,
+            // The `\n` of a `\r\n` pair does the counting.
+            '\r' if self.chars.peek() != Some(&'\n') => self.line += 1,
+            _ => {}
+        }
+        Some(character)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Cursor;
+
+    fn final_line(source: &str) -> usize {
+        let mut cursor = Cursor::new(source);
+        while cursor.next().is_some() {}
+        cursor.line()
+    }
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(final_line("a"), 1);
+        assert_eq!(final_line("a\nb"), 2);
+        assert_eq!(final_line("a\r\nb"), 2);
+        assert_eq!(final_line("a\rb"), 2);
+    }
+
+    #[test]
+    fn test_mixed_line_endings() {
+        assert_eq!(final_line("a\nb\r\nc\rd"), 4);
+        assert_eq!(final_line("\r\n\r\n\n\r\r"), 6);
+        assert_eq!(final_line("\r\r\n"), 3);
+    }
+
+    #[test]
+    fn test_peek_nth() {
+        let mut cursor = Cursor::new("aéb");
+        cursor.next();
+        assert_eq!(cursor.peek_nth(0), Some('é'));
+        assert_eq!(cursor.peek_nth(1), Some('b'));
+        assert_eq!(cursor.peek_nth(2), None);
+    }
+
+    #[test]
+    fn test_offset_counts_bytes() {
+        let mut cursor = Cursor::new("aé\r\nb");
+        while cursor.next().is_some() {}
+        assert_eq!(cursor.offset(), 6);
+    }
+}
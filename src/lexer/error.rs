@@ -0,0 +1,272 @@
+use std::error;
+use std::fmt;
+
+use super::span::Span;
+
+#[derive(PartialEq, Debug)]
+pub enum LexError {
+    InvalidUtf8 { offset: usize },
+    UnexpectedCharacter { character: char, span: Span },
+    IntOutOfRange { span: Span },
+    InvalidDeclaration { span: Span },
+    UnterminatedString { span: Span },
+    InvalidEscape { span: Span },
+    TabInIndentation { span: Span },
+    InvalidIntLiteral { span: Span },
+    MissingOperator { span: Span },
+    UnterminatedComment { span: Span },
+}
+
+impl LexError {
+    /// Stable identifier for the kind of error, for tools that filter or
+    /// suppress diagnostics. Codes are never reused or renumbered.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::InvalidUtf8 { .. } => "E0001",
+            LexError::UnexpectedCharacter { .. } => "E0002",
+            LexError::IntOutOfRange { .. } => "E0003",
+            LexError::InvalidDeclaration { .. } => "E0004",
+            LexError::UnterminatedString { .. } => "E0005",
+            LexError::InvalidEscape { .. } => "E0006",
+            LexError::TabInIndentation { .. } => "E0007",
+            LexError::InvalidIntLiteral { .. } => "E0008",
+            LexError::MissingOperator { .. } => "E0009",
+            LexError::UnterminatedComment { .. } => "E0010",
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexError::InvalidUtf8 { offset } => {
+                write!(f, "invalid UTF-8 sequence at byte offset {}", offset)
+            }
+            LexError::UnexpectedCharacter { character, span } => {
+                write!(f, "unexpected character {:?} at {}", character, span.start)
+            }
+            LexError::IntOutOfRange { span } => {
+                write!(f, "integer literal out of range at {}", span.start)
+            }
+            LexError::InvalidDeclaration { span } => {
+                write!(f, "invalid declaration at {}", span.start)
+            }
+            LexError::UnterminatedString { span } => {
+                write!(f, "unterminated string literal at {}", span.start)
+            }
+            LexError::InvalidEscape { span } => {
+                write!(f, "invalid escape sequence at {}", span.start)
+            }
+            LexError::TabInIndentation { span } => {
+                write!(f, "tab character in indentation at {}", span.start)
+            }
+            LexError::InvalidIntLiteral { span } => {
+                write!(f, "integer literal has no digits at {}", span.start)
+            }
+            LexError::MissingOperator { span } => {
+                write!(f, "missing operator before name at {}", span.start)
+            }
+            LexError::UnterminatedComment { span } => {
+                write!(f, "unterminated block comment at {}", span.start)
+            }
+        }
+    }
+}
+
+impl error::Error for LexError {}
+
+#[cfg(test)]
+mod test {
+    use super::{LexError, Span};
+    use crate::lexer::options::LexOptions;
+    use crate::lexer::tokenizer::Tokenizer;
+
+    #[test]
+    fn test_code() {
+        let span = Span::new(0, 1);
+        assert_eq!(LexError::InvalidUtf8 { offset: 0 }.code(), "E0001");
+        let error = LexError::UnexpectedCharacter {
+            character: '$',
+            span,
+        };
+        assert_eq!(error.code(), "E0002");
+        assert_eq!(LexError::UnterminatedString { span }.code(), "E0005");
+        assert_eq!(LexError::TabInIndentation { span }.code(), "E0007");
+    }
+
+    /// Malformed inputs and the error each one must produce, covering every
+    /// error path in the lexer. `InvalidUtf8` is only reachable from bytes
+    /// and is checked separately.
+    fn error_cases() -> Vec<(&'static str, LexOptions, LexError)> {
+        let default = LexOptions::default();
+        let no_tabs = LexOptions {
+            no_tabs: true,
+            ..LexOptions::default()
+        };
+        let unexpected = |character, start, end| LexError::UnexpectedCharacter {
+            character,
+            span: Span::new(start, end),
+        };
+        vec![
+            ("1 @", default, unexpected('@', 2, 3)),
+            ("é", default, unexpected('é', 0, 2)),
+            ("1 \\ 2", default, unexpected('\\', 2, 3)),
+            ("0b102", default, unexpected('2', 4, 5)),
+            ("1é", default, unexpected('é', 1, 3)),
+            (
+                "2147483648",
+                default,
+                LexError::IntOutOfRange {
+                    span: Span::new(0, 10),
+                },
+            ),
+            (
+                "0x80000000",
+                default,
+                LexError::IntOutOfRange {
+                    span: Span::new(0, 10),
+                },
+            ),
+            (
+                "int;",
+                default,
+                LexError::InvalidDeclaration {
+                    span: Span::new(0, 3),
+                },
+            ),
+            (
+                "int ;",
+                default,
+                LexError::InvalidDeclaration {
+                    span: Span::new(0, 4),
+                },
+            ),
+            (
+                "int x",
+                default,
+                LexError::InvalidDeclaration {
+                    span: Span::new(0, 5),
+                },
+            ),
+            (
+                "\"abc",
+                default,
+                LexError::UnterminatedString {
+                    span: Span::new(0, 4),
+                },
+            ),
+            (
+                "r#\"abc\"",
+                default,
+                LexError::UnterminatedString {
+                    span: Span::new(0, 7),
+                },
+            ),
+            (
+                "r##\"abc\"#",
+                default,
+                LexError::UnterminatedString {
+                    span: Span::new(0, 9),
+                },
+            ),
+            (
+                "\"\\q\"",
+                default,
+                LexError::InvalidEscape {
+                    span: Span::new(1, 3),
+                },
+            ),
+            (
+                "\"\\x4\"",
+                default,
+                LexError::InvalidEscape {
+                    span: Span::new(1, 4),
+                },
+            ),
+            (
+                "\"\\u{110000}\"",
+                default,
+                LexError::InvalidEscape {
+                    span: Span::new(1, 11),
+                },
+            ),
+            (
+                "\t1",
+                no_tabs,
+                LexError::TabInIndentation {
+                    span: Span::new(0, 1),
+                },
+            ),
+            (
+                "1\n  \t2",
+                no_tabs,
+                LexError::TabInIndentation {
+                    span: Span::new(4, 5),
+                },
+            ),
+            (
+                "0x",
+                default,
+                LexError::InvalidIntLiteral {
+                    span: Span::new(0, 2),
+                },
+            ),
+            (
+                "3abs",
+                default,
+                LexError::MissingOperator {
+                    span: Span::new(1, 4),
+                },
+            ),
+            (
+                "1 /* 2",
+                default,
+                LexError::UnterminatedComment {
+                    span: Span::new(2, 6),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_error_paths() {
+        for (source, options, expected) in error_cases() {
+            let error = Tokenizer::with_options(source.to_string(), options).unwrap_err();
+            assert_eq!(error, expected, "{:?}", source);
+        }
+
+        let error = Tokenizer::from_bytes(b"ab\xff").unwrap_err();
+        assert_eq!(error, LexError::InvalidUtf8 { offset: 2 });
+    }
+
+    /// Number of `LexError` variants, and the index of each one. The match
+    /// has no wildcard arm, so a new variant will not compile until it is
+    /// listed here and given a case in `error_cases`.
+    const VARIANT_COUNT: usize = 10;
+
+    fn variant_index(error: &LexError) -> usize {
+        match error {
+            LexError::InvalidUtf8 { .. } => 0,
+            LexError::UnexpectedCharacter { .. } => 1,
+            LexError::IntOutOfRange { .. } => 2,
+            LexError::InvalidDeclaration { .. } => 3,
+            LexError::UnterminatedString { .. } => 4,
+            LexError::InvalidEscape { .. } => 5,
+            LexError::TabInIndentation { .. } => 6,
+            LexError::InvalidIntLiteral { .. } => 7,
+            LexError::MissingOperator { .. } => 8,
+            LexError::UnterminatedComment { .. } => 9,
+        }
+    }
+
+    #[test]
+    fn test_error_paths_cover_every_variant() {
+        let mut seen = [false; VARIANT_COUNT];
+        for (_, _, error) in error_cases() {
+            seen[variant_index(&error)] = true;
+        }
+        seen[variant_index(&LexError::InvalidUtf8 { offset: 0 })] = true;
+        let missing: Vec<usize> = (0..VARIANT_COUNT).filter(|&index| !seen[index]).collect();
+        assert_eq!(missing, Vec::<usize>::new())
+    }
+}
@@ -1 +1,6 @@
+pub mod cursor;
+pub mod error;
+pub mod options;
+pub mod source;
+pub mod span;
 pub mod tokenizer;
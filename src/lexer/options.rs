@@ -0,0 +1,35 @@
+/// Punctuators recognised by default. The lexer always takes the longest
+/// entry that matches, so entries may be listed in any order.
+pub const PUNCTUATORS: &[&str] = &[
+    "...", "<<=", ">>=", "->", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "+=",
+    "-=", "*=", "/=", "%=", "&=", "|=", "^=", "+", "-", "*", "/", "%", "&", "|", "^", "!", "~",
+    "<", ">", "=", "?", ":", ",", ".", ";", "(", ")", "[", "]", "{", "}", "##", "#",
+];
+
+/// Switches that change what the lexer accepts. The defaults lex ordinary
+/// source.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct LexOptions {
+    /// Reject tab characters in a line's leading whitespace.
+    pub no_tabs: bool,
+    /// Punctuators to recognise, matched longest first. Empty entries are
+    /// ignored.
+    pub punctuators: &'static [&'static str],
+    /// Skip `//` and `/* */` comments. When unset, `/` and `*` always lex as
+    /// punctuators.
+    pub allow_comments: bool,
+    /// Distance between tab stops when computing columns. A tab moves the
+    /// column to the next multiple of this width; 0 is treated as 1.
+    pub tab_width: usize,
+}
+
+impl Default for LexOptions {
+    fn default() -> LexOptions {
+        LexOptions {
+            no_tabs: false,
+            punctuators: PUNCTUATORS,
+            allow_comments: true,
+            tab_width: 1,
+        }
+    }
+}
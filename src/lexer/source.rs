@@ -0,0 +1,151 @@
+use super::cursor::Cursor;
+use super::options::LexOptions;
+use super::span::{Position, Span};
+
+/// Source text kept around for rendering diagnostics.
+#[derive(PartialEq, Debug)]
+pub struct SourceFile {
+    source: String,
+    line_starts: Vec<usize>,
+    tab_width: usize,
+}
+
+impl SourceFile {
+    pub fn new(source: String) -> SourceFile {
+        SourceFile::with_options(source, LexOptions::default())
+    }
+
+    /// Like `new`, but computing columns with the options' `tab_width`.
+    pub fn with_options(source: String, options: LexOptions) -> SourceFile {
+        let mut line_starts = vec![0];
+        let mut cursor = Cursor::new(&source);
+        while cursor.next().is_some() {
+            if cursor.line() > line_starts.len() {
+                line_starts.push(cursor.offset());
+            }
+        }
+        SourceFile {
+            source,
+            line_starts,
+            tab_width: options.tab_width.max(1),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.source
+    }
+
+    /// Byte offset at which each line starts, in order. Line breaks are
+    /// counted the same way as by the lexer.
+    pub fn line_starts(&self) -> &[usize] {
+        &self.line_starts
+    }
+
+    /// Text covered by `span`, or `None` if the span runs past the end of
+    /// the source or doesn't fall on character boundaries.
+    pub fn slice(&self, span: Span) -> Option<&str> {
+        self.source.get(span.start..span.end)
+    }
+
+    /// Text of the 1-based `line`, without its line ending.
+    pub fn line_text(&self, line: usize) -> Option<&str> {
+        let start = *self.line_starts.get(line.checked_sub(1)?)?;
+        let rest = &self.source[start..];
+        let end = rest.find(['\n', '\r']).unwrap_or(rest.len());
+        Some(&rest[..end])
+    }
+
+    /// Line and column of the byte `offset`, or `None` if it lies past the
+    /// end of the source or inside a character.
+    pub fn position_at(&self, offset: usize) -> Option<Position> {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        let column = self
+            .source
+            .get(line_start..offset)?
+            .chars()
+            .fold(0, |column, c| match c {
+                '\t' => (column / self.tab_width + 1) * self.tab_width,
+                _ => column + 1,
+            });
+        Some(Position::new(line, column + 1))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{LexOptions, Position, SourceFile, Span};
+    use crate::lexer::tokenizer::Lexer;
+
+    #[test]
+    fn test_slice() {
+        let source = SourceFile::new(String::from("int é;\n\"ü\" 12"));
+        assert_eq!(source.slice(Span::new(0, 3)), Some("int"));
+        assert_eq!(source.slice(Span::new(4, 6)), Some("é"));
+        assert_eq!(source.slice(Span::new(8, 12)), Some("\"ü\""));
+        assert_eq!(source.slice(Span::new(13, 15)), Some("12"));
+        assert_eq!(source.slice(Span::new(4, 5)), None);
+        assert_eq!(source.slice(Span::new(13, 20)), None);
+    }
+
+    #[test]
+    fn test_line_text() {
+        let source = SourceFile::new(String::from("one\ntwo\r\nthree\rfour\n"));
+        assert_eq!(source.line_text(0), None);
+        assert_eq!(source.line_text(1), Some("one"));
+        assert_eq!(source.line_text(2), Some("two"));
+        assert_eq!(source.line_text(3), Some("three"));
+        assert_eq!(source.line_text(4), Some("four"));
+        assert_eq!(source.line_text(5), Some(""));
+        assert_eq!(source.line_text(6), None);
+    }
+
+    #[test]
+    fn test_line_starts() {
+        let source = SourceFile::new(String::from("one\ntwo\r\nthree\rfour\n"));
+        assert_eq!(source.line_starts(), &[0, 4, 9, 15, 20]);
+        assert_eq!(SourceFile::new(String::new()).line_starts(), &[0]);
+    }
+
+    #[test]
+    fn test_position_at() {
+        let source = SourceFile::new(String::from("ab\r\nçd\n\nx"));
+        assert_eq!(source.position_at(0), Some(Position::new(1, 1)));
+        assert_eq!(source.position_at(1), Some(Position::new(1, 2)));
+        assert_eq!(source.position_at(2), Some(Position::new(1, 3)));
+        assert_eq!(source.position_at(4), Some(Position::new(2, 1)));
+        assert_eq!(source.position_at(6), Some(Position::new(2, 2)));
+        assert_eq!(source.position_at(8), Some(Position::new(3, 1)));
+        assert_eq!(source.position_at(9), Some(Position::new(4, 1)));
+        assert_eq!(source.position_at(10), Some(Position::new(4, 2)));
+        assert_eq!(source.position_at(5), None);
+        assert_eq!(source.position_at(11), None);
+
+        let source = SourceFile::new(String::from("é"));
+        assert_eq!(source.position_at(1), None);
+        assert_eq!(source.position_at(2), Some(Position::new(1, 2)));
+    }
+
+    fn column_after_tab(source: &str, tab_width: usize) -> usize {
+        let options = LexOptions {
+            tab_width,
+            ..LexOptions::default()
+        };
+        let (_, span) = Lexer::with_options(source, options)
+            .nth(1)
+            .unwrap()
+            .unwrap();
+        let source = SourceFile::with_options(source.to_string(), options);
+        source.position_at(span.start).unwrap().column
+    }
+
+    #[test]
+    fn test_tab_width() {
+        assert_eq!(column_after_tab("1\t2", 1), 3);
+        assert_eq!(column_after_tab("1\t2", 4), 5);
+        assert_eq!(column_after_tab("123\t4", 4), 5);
+        assert_eq!(column_after_tab("1234\t5", 4), 9);
+        assert_eq!(column_after_tab("1 \t\t2", 4), 9);
+        assert_eq!(column_after_tab("\t1\n\t 2", 4), 6);
+    }
+}
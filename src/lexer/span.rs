@@ -0,0 +1,26 @@
+/// Half-open range of byte offsets into the source text.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+}
+
+/// 1-based line and column of a character in the source text. Columns count
+/// characters, not bytes.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Position {
+    pub fn new(line: usize, column: usize) -> Position {
+        Position { line, column }
+    }
+}
@@ -2,14 +2,21 @@ use std::iter;
 use std::mem;
 use std::str;
 
+use super::cursor::Cursor;
+use super::error::LexError;
+use super::options::LexOptions;
+use super::span::Span;
+
 #[derive(PartialEq, Debug)]
 pub enum LiteralType {
     Int(i32),
+    Str(String),
 }
 
 #[derive(PartialEq, Debug)]
 pub enum IdentifierType {
     Var(Identifier),
+    Name(String),
 }
 
 #[derive(PartialEq, Debug)]
@@ -22,6 +29,7 @@ pub struct Identifier {
 pub enum Token {
     Literal(LiteralType),
     Identifier(IdentifierType),
+    Punctuator(String),
 }
 
 #[derive(PartialEq, Debug)]
@@ -30,78 +38,519 @@ pub struct Tokenizer {
 }
 
 impl Tokenizer {
-    pub fn tokenize_int_literal(iter: &mut iter::Peekable<str::Chars>) -> Token {
+    /// Tokenizes a decimal integer literal, or a hexadecimal, octal or
+    /// binary one with a `0x`, `0o` or `0b` prefix. Leading zeros without a
+    /// prefix are still decimal, so `007` is 7.
+    pub fn tokenize_int_literal(iter: &mut Cursor) -> Result<Token, LexError> {
+        let start = iter.offset();
+        let radix = match (iter.peek_nth(0), iter.peek_nth(1)) {
+            (Some('0'), Some('x' | 'X')) => 16,
+            (Some('0'), Some('o' | 'O')) => 8,
+            (Some('0'), Some('b' | 'B')) => 2,
+            _ => 10,
+        };
+        if radix != 10 {
+            iter.next();
+            iter.next();
+        }
         let mut token_buffer = String::with_capacity(mem::size_of::<i32>());
-        while let Some(digit) = iter.next() {
-            if digit.is_digit(10) {
-                token_buffer.insert(token_buffer.len(), digit)
+        while let Some(&digit) = iter.peek() {
+            if !digit.is_digit(radix) {
+                break;
             }
+            token_buffer.push(digit);
+            iter.next();
+        }
+        let end = iter.offset();
+        if token_buffer.is_empty() {
+            Tokenizer::skip_word_rest(iter);
+            return Err(LexError::InvalidIntLiteral {
+                span: Span::new(start, end),
+            });
         }
 
-        if let Some(after_digit) = iter.peek() {
-            if !after_digit.is_whitespace() {
-                todo!("Handle error case");
+        if let Some(&after_digit) = iter.peek() {
+            // `3abs` is almost certainly a number and a name with the
+            // operator between them missing, so point at the whole name.
+            if after_digit.is_ascii_alphabetic() || after_digit == '_' {
+                Tokenizer::parse_alpha_num(iter);
+                return Err(LexError::MissingOperator {
+                    span: Span::new(end, iter.offset()),
+                });
+            }
+            if after_digit.is_alphanumeric() {
+                iter.next();
+                let error = LexError::UnexpectedCharacter {
+                    character: after_digit,
+                    span: Span::new(end, iter.offset()),
+                };
+                Tokenizer::skip_word_rest(iter);
+                return Err(error);
+            }
+        }
+        let token_value =
+            i32::from_str_radix(&token_buffer, radix).map_err(|_| LexError::IntOutOfRange {
+                span: Span::new(start, end),
+            })?;
+        Ok(Token::Literal(LiteralType::Int(token_value)))
+    }
+
+    /// Skips the rest of a malformed literal, so none of it is lexed again
+    /// as tokens of its own.
+    fn skip_word_rest(iter: &mut Cursor) {
+        while iter
+            .peek()
+            .is_some_and(|&c| c.is_alphanumeric() || c == '_')
+        {
+            iter.next();
+        }
+    }
+
+    pub fn tokenize_string_literal(iter: &mut Cursor) -> Result<Token, LexError> {
+        let start = iter.offset();
+        iter.next();
+        let mut token_buffer = String::new();
+        // An invalid escape doesn't end the literal; keep going to the closing
+        // quote so lexing resumes after it.
+        let mut escape_error = None;
+        loop {
+            let escape_start = iter.offset();
+            match iter.next() {
+                Some('"') => break,
+                Some('\\') => match Tokenizer::parse_escape(iter) {
+                    Some(c) => token_buffer.push(c),
+                    None => {
+                        escape_error.get_or_insert(LexError::InvalidEscape {
+                            span: Span::new(escape_start, iter.offset()),
+                        });
+                    }
+                },
+                Some(c) => token_buffer.push(c),
+                None => {
+                    return Err(LexError::UnterminatedString {
+                        span: Span::new(start, iter.offset()),
+                    })
+                }
+            }
+        }
+        match escape_error {
+            Some(error) => Err(error),
+            None => Ok(Token::Literal(LiteralType::Str(token_buffer))),
+        }
+    }
+
+    /// Parses the escape after a `\\`. Returns `None` for a malformed escape,
+    /// without consuming past the first character that doesn't fit it. As in
+    /// Rust, `\xHH` must be ASCII; other characters need `\u{...}`.
+    fn parse_escape(iter: &mut Cursor) -> Option<char> {
+        match iter.next()? {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' => Some('\\'),
+            '"' => Some('"'),
+            '\'' => Some('\''),
+            'x' => {
+                let digits = Tokenizer::parse_hex_digits(iter, 2);
+                if digits.len() != 2 {
+                    return None;
+                }
+                u8::from_str_radix(&digits, 16)
+                    .ok()
+                    .filter(u8::is_ascii)
+                    .map(char::from)
+            }
+            'u' => {
+                if iter.peek() != Some(&'{') {
+                    return None;
+                }
+                iter.next();
+                let digits = Tokenizer::parse_hex_digits(iter, 6);
+                if iter.peek() != Some(&'}') {
+                    return None;
+                }
+                iter.next();
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_hex_digits(iter: &mut Cursor, max: usize) -> String {
+        let mut token_buffer = String::new();
+        while token_buffer.len() < max {
+            match iter.peek() {
+                Some(&c) if c.is_ascii_hexdigit() => token_buffer.push(c),
+                _ => break,
+            }
+            iter.next();
+        }
+        token_buffer
+    }
+
+    /// Tokenizes the rest of a raw string literal, `r"..."` or `r#"..."#`.
+    /// `start` is the offset of the `r` prefix, which has already been
+    /// consumed, and the cursor must be at the `#`s and `"` that open the
+    /// literal. No escapes are processed; the literal ends at the first `"`
+    /// followed by as many `#` as opened it.
+    fn tokenize_raw_string_literal(iter: &mut Cursor, start: usize) -> Result<Token, LexError> {
+        let mut hashes = 0;
+        while iter.peek() == Some(&'#') {
+            hashes += 1;
+            iter.next();
+        }
+        iter.next();
+
+        let mut token_buffer = String::new();
+        loop {
+            match iter.next() {
+                Some('"') => {
+                    let mut closing = 0;
+                    while closing < hashes && iter.peek() == Some(&'#') {
+                        closing += 1;
+                        iter.next();
+                    }
+                    if closing == hashes {
+                        break;
+                    }
+                    token_buffer.push('"');
+                    token_buffer.extend(iter::repeat_n('#', closing));
+                }
+                Some(c) => token_buffer.push(c),
+                None => {
+                    return Err(LexError::UnterminatedString {
+                        span: Span::new(start, iter.offset()),
+                    })
+                }
             }
         }
-        let token_value = token_buffer.parse::<i32>().unwrap();
-        Token::Literal(LiteralType::Int(token_value))
+        Ok(Token::Literal(LiteralType::Str(token_buffer)))
     }
-    pub fn parse_alpha_num(iter: &mut iter::Peekable<str::Chars>) -> String {
+
+    pub fn parse_alpha_num(iter: &mut Cursor) -> String {
         let mut token_buffer = String::new();
-        while let Some(c) = iter.next() {
-            if c.is_ascii_alphanumeric() || c == '_' {
-                token_buffer = String::from(token_buffer + &c.to_string());
+        while let Some(&c) = iter.peek() {
+            if !(c.is_ascii_alphanumeric() || c == '_') {
+                break;
             }
+            token_buffer.push(c);
+            iter.next();
         }
         token_buffer
     }
 
+    /// Whether the cursor is at a `\` that ends its line.
+    fn at_line_continuation(iter: &Cursor) -> bool {
+        iter.peek_nth(0) == Some('\\') && matches!(iter.peek_nth(1), Some('\n') | Some('\r'))
+    }
+
+    fn skip_line_continuation(iter: &mut Cursor) {
+        iter.next();
+        if iter.next() == Some('\r') && iter.peek() == Some(&'\n') {
+            iter.next();
+        }
+    }
+
+    /// Tokenizes the longest of `punctuators` that starts at the cursor, if
+    /// any does.
+    pub fn tokenize_punctuator(iter: &mut Cursor, punctuators: &[&str]) -> Option<Token> {
+        let punctuator = punctuators
+            .iter()
+            .filter(|punctuator| !punctuator.is_empty() && iter.rest().starts_with(**punctuator))
+            .max_by_key(|punctuator| punctuator.len())?;
+        for _ in punctuator.chars() {
+            iter.next();
+        }
+        Some(Token::Punctuator(punctuator.to_string()))
+    }
+
     fn is_reserved(string: &str) -> bool {
-        match string {
-            "int" => true,
-            _ => false,
+        matches!(string, "int")
+    }
+
+    pub fn from_string(string: String) -> Result<Tokenizer, LexError> {
+        Tokenizer::with_options(string, LexOptions::default())
+    }
+
+    pub fn with_options(string: String, options: LexOptions) -> Result<Tokenizer, LexError> {
+        let tokens = Lexer::with_options(&string, options)
+            .map(|result| result.map(|(token, _)| token))
+            .collect::<Result<Vec<Token>, LexError>>()?;
+
+        Ok(Tokenizer { tokens })
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Tokenizer, LexError> {
+        let string = str::from_utf8(bytes).map_err(|error| LexError::InvalidUtf8 {
+            offset: error.valid_up_to(),
+        })?;
+        Tokenizer::from_string(string.to_string())
+    }
+}
+
+/// Lazily tokenizes a source string, yielding each token with its span.
+///
+/// After an error, lexing carries on with the next token. A run of characters
+/// that cannot start any token is reported once, as an unexpected character
+/// at its start.
+pub struct Lexer<'a> {
+    iter: Cursor<'a>,
+    options: LexOptions,
+    /// Nothing but whitespace and comments since the last line break; a tab
+    /// here is indentation.
+    at_line_start: bool,
+    /// A line break outside any comment has been skipped. Comments count as
+    /// a single space, so this is what ends a statement.
+    after_line_break: bool,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Lexer<'a> {
+        Lexer::with_options(source, LexOptions::default())
+    }
+
+    pub fn with_options(source: &'a str, options: LexOptions) -> Lexer<'a> {
+        Lexer {
+            iter: Cursor::new(source),
+            options,
+            at_line_start: true,
+            after_line_break: false,
         }
     }
 
-    pub fn from_string(string: String) -> Tokenizer {
-        let mut iter = string.chars().into_iter().peekable();
+    fn skip_block_comment(&mut self) -> Result<(), LexError> {
+        let start = self.iter.offset();
+        self.iter.next();
+        self.iter.next();
+        while !self.iter.rest().starts_with("*/") {
+            match self.iter.next() {
+                Some('\n') | Some('\r') => self.at_line_start = true,
+                Some(_) => {}
+                None => {
+                    return Err(LexError::UnterminatedComment {
+                        span: Span::new(start, self.iter.offset()),
+                    })
+                }
+            }
+        }
+        self.iter.next();
+        self.iter.next();
+        Ok(())
+    }
 
-        let mut tokens: Vec<Token> = Vec::new();
+    /// Skips one line continuation, comment or whitespace character,
+    /// returning whether there was one. When `check_tabs` is set, a tab in a
+    /// line's leading whitespace is reported if `no_tabs` is on.
+    fn skip_trivia(&mut self, check_tabs: bool) -> Result<bool, LexError> {
+        if Tokenizer::at_line_continuation(&self.iter) {
+            Tokenizer::skip_line_continuation(&mut self.iter);
+            return Ok(true);
+        }
+        if self.options.allow_comments && self.iter.rest().starts_with("//") {
+            loop {
+                if Tokenizer::at_line_continuation(&self.iter) {
+                    Tokenizer::skip_line_continuation(&mut self.iter);
+                } else if self.iter.peek().is_some_and(|&c| c != '\n' && c != '\r') {
+                    self.iter.next();
+                } else {
+                    break;
+                }
+            }
+            return Ok(true);
+        }
+        if self.options.allow_comments && self.iter.rest().starts_with("/*") {
+            self.skip_block_comment()?;
+            return Ok(true);
+        }
+        match self.iter.peek() {
+            Some('\t') if check_tabs && self.options.no_tabs && self.at_line_start => {
+                let start = self.iter.offset();
+                self.iter.next();
+                // One error per line is enough, not one per tab.
+                self.at_line_start = false;
+                Err(LexError::TabInIndentation {
+                    span: Span::new(start, self.iter.offset()),
+                })
+            }
+            Some(&c) if c.is_whitespace() => {
+                if c == '\n' || c == '\r' {
+                    self.at_line_start = true;
+                    self.after_line_break = true;
+                }
+                self.iter.next();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
 
-        while let Some(character) = iter.peek() {
-            if character.is_alphabetic() {
-                let token_buffer = Tokenizer::parse_alpha_num(&mut iter);
-                if Tokenizer::is_reserved(token_buffer.as_str()) {}
-                let token = match token_buffer.as_str() {
-                    "int" => {
-                        if let Some(c) = iter.peek() {
-                            if !c.is_whitespace() || *c != ';' {
-                                todo!("Handle error case");
-                            }
-                            iter.next();
-                        }
-                    }
-                    _ => {}
-                };
-            } else if character.is_digit(10) {
-                let token = Tokenizer::tokenize_int_literal(&mut iter);
-                tokens.push(token);
+    /// Skips whitespace and comments before the next token, reporting a tab
+    /// in a line's leading whitespace when `no_tabs` is set.
+    fn skip_whitespace(&mut self) -> Result<(), LexError> {
+        while self.skip_trivia(true)? {}
+        Ok(())
+    }
+
+    /// Discards tokens, and any errors among them, up to and including the
+    /// next `;` or line break, so lexing can resume at the start of the next
+    /// statement after an error.
+    pub fn skip_to_statement_boundary(&mut self) {
+        self.after_line_break = false;
+        loop {
+            // Stop right after the line break, so the next line's indentation
+            // is still checked. An error here is a block comment running to
+            // the end of input.
+            loop {
+                match self.skip_trivia(false) {
+                    Ok(true) if self.after_line_break => return,
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    Err(_) => return,
+                }
+            }
+            match self.next() {
+                Some(Ok((Token::Punctuator(punctuator), _))) if punctuator == ";" => return,
+                Some(_) => {}
+                None => return,
+            }
+        }
+    }
+
+    /// Skips characters that cannot start a token, stopping at whitespace or
+    /// at the first character that can.
+    fn skip_bad_run(&mut self) {
+        while let Some(&c) = self.iter.peek() {
+            let starts_token = c.is_whitespace()
+                || c.is_ascii_alphanumeric()
+                || c == '_'
+                || c == '"'
+                || Tokenizer::at_line_continuation(&self.iter)
+                || self.options.punctuators.iter().any(|punctuator| {
+                    !punctuator.is_empty() && self.iter.rest().starts_with(punctuator)
+                });
+            if starts_token {
                 break;
             }
+            self.iter.next();
         }
+    }
 
-        Tokenizer { tokens }
+    /// Tokenizes the rest of an `int <label>;` declaration. `start` is the
+    /// offset of the `int` keyword, which has already been consumed. Tabs
+    /// inside the declaration are not checked, since a style error should
+    /// not cost the declaration.
+    fn tokenize_int_declaration(&mut self, start: usize) -> Result<Token, LexError> {
+        let invalid = |iter: &Cursor| LexError::InvalidDeclaration {
+            span: Span::new(start, iter.offset()),
+        };
+        if !self.skip_trivia(false)? {
+            return Err(invalid(&self.iter));
+        }
+        while self.skip_trivia(false)? {}
+        let label = Tokenizer::parse_alpha_num(&mut self.iter);
+        if label.is_empty() {
+            return Err(invalid(&self.iter));
+        }
+        while self.skip_trivia(false)? {}
+        if self.iter.next() != Some(';') {
+            return Err(invalid(&self.iter));
+        }
+        Ok(Token::Identifier(IdentifierType::Var(Identifier {
+            value: LiteralType::Int(0),
+            label,
+        })))
+    }
+
+    fn tokenize(&mut self, start: usize, character: char) -> Result<Token, LexError> {
+        if character.is_ascii_alphabetic() || character == '_' {
+            let token_buffer = Tokenizer::parse_alpha_num(&mut self.iter);
+            // `r#x` is a name followed by a `#` punctuator, not a raw string.
+            if token_buffer == "r" && self.iter.rest().trim_start_matches('#').starts_with('"') {
+                return Tokenizer::tokenize_raw_string_literal(&mut self.iter, start);
+            }
+            if !Tokenizer::is_reserved(token_buffer.as_str()) {
+                return Ok(Token::Identifier(IdentifierType::Name(token_buffer)));
+            }
+            match token_buffer.as_str() {
+                "int" => self.tokenize_int_declaration(start),
+                _ => unreachable!(),
+            }
+        } else if character.is_ascii_digit() {
+            Tokenizer::tokenize_int_literal(&mut self.iter)
+        } else if character == '"' {
+            Tokenizer::tokenize_string_literal(&mut self.iter)
+        } else if let Some(token) =
+            Tokenizer::tokenize_punctuator(&mut self.iter, self.options.punctuators)
+        {
+            Ok(token)
+        } else {
+            self.iter.next();
+            let error = LexError::UnexpectedCharacter {
+                character,
+                span: Span::new(start, self.iter.offset()),
+            };
+            self.skip_bad_run();
+            Err(error)
+        }
     }
 }
 
+impl Iterator for Lexer<'_> {
+    type Item = Result<(Token, Span), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Err(error) = self.skip_whitespace() {
+            return Some(Err(error));
+        }
+        let start = self.iter.offset();
+        let &character = self.iter.peek()?;
+        self.at_line_start = false;
+        let result = self.tokenize(start, character);
+        // A declaration can span lines, but a token never ends at the start
+        // of one.
+        self.at_line_start = false;
+        Some(result.map(|token| (token, Span::new(start, self.iter.offset()))))
+    }
+}
+
+/// Lexes the whole source, handing each error to `on_error` as soon as it is
+/// found rather than after the whole input has been processed.
+pub fn lex_with_diagnostics<F>(source: &str, mut on_error: F) -> Vec<(Token, Span)>
+where
+    F: FnMut(LexError),
+{
+    let mut tokens = Vec::new();
+    for result in Lexer::new(source) {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(error) => on_error(error),
+        }
+    }
+    tokens
+}
+
+/// Lexes the whole source, collecting every error instead of stopping at the
+/// first one.
+pub fn lex_all_lenient(source: &str) -> (Vec<(Token, Span)>, Vec<LexError>) {
+    let mut errors = Vec::new();
+    let tokens = lex_with_diagnostics(source, |error| errors.push(error));
+    (tokens, errors)
+}
+
 #[cfg(test)]
 mod test {
-    use super::{Identifier, IdentifierType, LiteralType, Token, Tokenizer};
+    use super::{
+        lex_all_lenient, lex_with_diagnostics, Identifier, IdentifierType, LexError, LexOptions,
+        Lexer, LiteralType, Span, Token, Tokenizer,
+    };
 
     #[test]
     fn test_tokenize_int_literal() {
         let string_tokens = "1";
-        let tokens = Tokenizer::from_string(string_tokens.to_string());
+        let tokens = Tokenizer::from_string(string_tokens.to_string()).unwrap();
         let expected = Tokenizer {
             tokens: vec![Token::Literal(LiteralType::Int(1))],
         };
@@ -111,7 +560,7 @@ mod test {
     #[test]
     fn test_tokenize_itentifier() {
         let string_tokens = "int num;";
-        let tokens = Tokenizer::from_string(string_tokens.to_string());
+        let tokens = Tokenizer::from_string(string_tokens.to_string()).unwrap();
         let expected = Tokenizer {
             tokens: vec![Token::Identifier(IdentifierType::Var(Identifier {
                 value: LiteralType::Int(0),
@@ -120,4 +569,656 @@ mod test {
         };
         assert_eq!(tokens, expected)
     }
+
+    #[test]
+    fn test_from_bytes() {
+        let tokens = Tokenizer::from_bytes("int num;".as_bytes()).unwrap();
+        let expected = Tokenizer::from_string("int num;".to_string()).unwrap();
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_from_bytes_invalid_utf8() {
+        let bytes = [b'1', b' ', 0xe2, 0x28, 0xa1];
+        let error = Tokenizer::from_bytes(&bytes).unwrap_err();
+        assert_eq!(error, LexError::InvalidUtf8 { offset: 2 })
+    }
+
+    #[test]
+    fn test_from_string_stops_at_first_error() {
+        let error = Tokenizer::from_string("1 $ 2 @".to_string()).unwrap_err();
+        let expected = LexError::UnexpectedCharacter {
+            character: '$',
+            span: Span::new(2, 3),
+        };
+        assert_eq!(error, expected)
+    }
+
+    #[test]
+    fn test_lex_all_lenient() {
+        let (tokens, errors) = lex_all_lenient("1 $$ 2 34x 5");
+        let expected_tokens = vec![
+            (Token::Literal(LiteralType::Int(1)), Span::new(0, 1)),
+            (Token::Literal(LiteralType::Int(2)), Span::new(5, 6)),
+            (Token::Literal(LiteralType::Int(5)), Span::new(11, 12)),
+        ];
+        let expected_errors = vec![
+            LexError::UnexpectedCharacter {
+                character: '$',
+                span: Span::new(2, 3),
+            },
+            LexError::MissingOperator {
+                span: Span::new(9, 10),
+            },
+        ];
+        assert_eq!(tokens, expected_tokens);
+        assert_eq!(errors, expected_errors)
+    }
+
+    #[test]
+    fn test_lex_all_lenient_keeps_tokens_after_error() {
+        let (tokens, errors) = lex_all_lenient("f(3x) + 1 $; 2 $$@;");
+        let tokens: Vec<Token> = tokens.into_iter().map(|(token, _)| token).collect();
+        let expected = vec![
+            Token::Identifier(IdentifierType::Name(String::from("f"))),
+            Token::Punctuator(String::from("(")),
+            Token::Punctuator(String::from(")")),
+            Token::Punctuator(String::from("+")),
+            Token::Literal(LiteralType::Int(1)),
+            Token::Punctuator(String::from(";")),
+            Token::Literal(LiteralType::Int(2)),
+            Token::Punctuator(String::from(";")),
+        ];
+        assert_eq!(tokens, expected);
+        let expected_errors = vec![
+            LexError::MissingOperator {
+                span: Span::new(3, 4),
+            },
+            LexError::UnexpectedCharacter {
+                character: '$',
+                span: Span::new(10, 11),
+            },
+            LexError::UnexpectedCharacter {
+                character: '$',
+                span: Span::new(15, 16),
+            },
+        ];
+        assert_eq!(errors, expected_errors)
+    }
+
+    #[test]
+    fn test_tokenize_string_literal() {
+        let tokens = Tokenizer::from_string(r#""a\n\"b\"""#.to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![Token::Literal(LiteralType::Str(String::from("a\n\"b\"")))],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_tokenize_raw_string_literal() {
+        let tokens = Tokenizer::from_string(r#"r"a\n""#.to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![Token::Literal(LiteralType::Str(String::from("a\\n")))],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_tokenize_raw_string_literal_with_hashes() {
+        let tokens =
+            Tokenizer::from_string(r###"r#"say "hi""# r##"a"#b"##"###.to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Literal(LiteralType::Str(String::from("say \"hi\""))),
+                Token::Literal(LiteralType::Str(String::from("a\"#b"))),
+            ],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_unterminated_string_literal() {
+        for source in [r#""abc"#, r#"r"abc"#, r###"r#"abc""###, r###"r##"abc"#"###] {
+            let error = Tokenizer::from_string(source.to_string()).unwrap_err();
+            assert!(
+                matches!(error, LexError::UnterminatedString { span } if span.start == 0),
+                "{:?} gave {:?}",
+                source,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_escape_resumes_after_string() {
+        let (tokens, errors) = lex_all_lenient(r#""a\qb" 1"#);
+        assert_eq!(
+            tokens,
+            vec![(Token::Literal(LiteralType::Int(1)), Span::new(7, 8))]
+        );
+        assert_eq!(
+            errors,
+            vec![LexError::InvalidEscape {
+                span: Span::new(2, 4)
+            }]
+        );
+
+        let (tokens, errors) = lex_all_lenient(r#""a\q";c"#);
+        let expected = vec![
+            (Token::Punctuator(String::from(";")), Span::new(5, 6)),
+            (
+                Token::Identifier(IdentifierType::Name(String::from("c"))),
+                Span::new(6, 7),
+            ),
+        ];
+        assert_eq!(tokens, expected);
+        assert_eq!(
+            errors,
+            vec![LexError::InvalidEscape {
+                span: Span::new(2, 4)
+            }]
+        )
+    }
+
+    #[test]
+    fn test_line_continuation() {
+        for source in ["1 \\\n2", "1\\\n2", "1 \\\r\n 2", "1\\\r2"] {
+            let tokens = Tokenizer::from_string(source.to_string()).unwrap();
+            let expected = Tokenizer {
+                tokens: vec![
+                    Token::Literal(LiteralType::Int(1)),
+                    Token::Literal(LiteralType::Int(2)),
+                ],
+            };
+            assert_eq!(tokens, expected, "{:?}", source)
+        }
+    }
+
+    #[test]
+    fn test_backslash_not_at_line_end() {
+        for (source, offset) in [("1 \\ 2", 2), ("1 \\", 2), ("1\\ \n2", 1)] {
+            let error = Tokenizer::from_string(source.to_string()).unwrap_err();
+            let expected = LexError::UnexpectedCharacter {
+                character: '\\',
+                span: Span::new(offset, offset + 1),
+            };
+            assert_eq!(error, expected, "{:?}", source)
+        }
+    }
+
+    #[test]
+    fn test_tab_in_indentation() {
+        let options = LexOptions {
+            no_tabs: true,
+            ..LexOptions::default()
+        };
+        let results: Vec<_> = Lexer::with_options("1\n\t\t2 \t3\n  \t4", options).collect();
+        let expected = vec![
+            Ok((Token::Literal(LiteralType::Int(1)), Span::new(0, 1))),
+            Err(LexError::TabInIndentation {
+                span: Span::new(2, 3),
+            }),
+            Ok((Token::Literal(LiteralType::Int(2)), Span::new(4, 5))),
+            Ok((Token::Literal(LiteralType::Int(3)), Span::new(7, 8))),
+            Err(LexError::TabInIndentation {
+                span: Span::new(11, 12),
+            }),
+            Ok((Token::Literal(LiteralType::Int(4)), Span::new(12, 13))),
+        ];
+        assert_eq!(results, expected)
+    }
+
+    #[test]
+    fn test_tab_in_indentation_allowed_by_default() {
+        let tokens = Tokenizer::from_string("\t1\n\t2".to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Literal(LiteralType::Int(1)),
+                Token::Literal(LiteralType::Int(2)),
+            ],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_lex_with_diagnostics_reports_in_discovery_order() {
+        let mut seen = Vec::new();
+        let tokens = lex_with_diagnostics("$ 1\n2\n3 @", |error| seen.push(error));
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            seen,
+            vec![
+                LexError::UnexpectedCharacter {
+                    character: '$',
+                    span: Span::new(0, 1),
+                },
+                LexError::UnexpectedCharacter {
+                    character: '@',
+                    span: Span::new(8, 9),
+                },
+            ]
+        )
+    }
+
+    #[test]
+    fn test_hex_and_unicode_escapes() {
+        let tokens = Tokenizer::from_string(r#""\x41\x7a" "\u{1F600}\u{e9}""#.to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Literal(LiteralType::Str(String::from("Az"))),
+                Token::Literal(LiteralType::Str(String::from("\u{1F600}\u{e9}"))),
+            ],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_invalid_hex_and_unicode_escapes() {
+        let cases = [
+            (r#""\x1""#, Span::new(1, 4)),
+            (r#""\xg1""#, Span::new(1, 3)),
+            (r#""\x80""#, Span::new(1, 5)),
+            (r#""\xff""#, Span::new(1, 5)),
+            (r#""a\u{110000}""#, Span::new(2, 12)),
+            (r#""\u{D800}""#, Span::new(1, 9)),
+            (r#""\u{}""#, Span::new(1, 5)),
+            (r#""\u41""#, Span::new(1, 3)),
+            (r#""\u{1234567}""#, Span::new(1, 10)),
+            (r#""\u{41""#, Span::new(1, 6)),
+        ];
+        for (source, span) in cases {
+            let error = Tokenizer::from_string(source.to_string()).unwrap_err();
+            assert_eq!(error, LexError::InvalidEscape { span }, "{}", source)
+        }
+    }
+
+    #[test]
+    fn test_leading_zero_and_radix_literals() {
+        let cases = [
+            ("0", 0),
+            ("00", 0),
+            ("007", 7),
+            ("0x10", 16),
+            ("0X1f", 31),
+            ("0o17", 15),
+            ("0b101", 5),
+            ("0x7fffffff", i32::MAX),
+        ];
+        for (source, value) in cases {
+            let tokens = Tokenizer::from_string(source.to_string()).unwrap();
+            let expected = Tokenizer {
+                tokens: vec![Token::Literal(LiteralType::Int(value))],
+            };
+            assert_eq!(tokens, expected, "{}", source)
+        }
+    }
+
+    #[test]
+    fn test_invalid_radix_literals() {
+        let error = Tokenizer::from_string("0x ".to_string()).unwrap_err();
+        assert_eq!(
+            error,
+            LexError::InvalidIntLiteral {
+                span: Span::new(0, 2)
+            }
+        );
+        let error = Tokenizer::from_string("0b102".to_string()).unwrap_err();
+        assert_eq!(
+            error,
+            LexError::UnexpectedCharacter {
+                character: '2',
+                span: Span::new(4, 5)
+            }
+        );
+        let error = Tokenizer::from_string("0x80000000".to_string()).unwrap_err();
+        assert_eq!(
+            error,
+            LexError::IntOutOfRange {
+                span: Span::new(0, 10)
+            }
+        );
+
+        for source in ["0b10234 1", "0b2 1", "0xg 1", "0x_é 1", "1é2 1"] {
+            let (tokens, errors) = lex_all_lenient(source);
+            let expected = (
+                Token::Literal(LiteralType::Int(1)),
+                Span::new(source.len() - 1, source.len()),
+            );
+            assert_eq!(tokens, vec![expected], "{:?}", source);
+            assert_eq!(errors.len(), 1, "{:?}", source);
+        }
+    }
+
+    fn punctuators(tokens: Tokenizer) -> Vec<String> {
+        tokens
+            .tokens
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Punctuator(punctuator) => Some(punctuator),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_punctuators() {
+        let tokens = Tokenizer::from_string("1<<=2;".to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Literal(LiteralType::Int(1)),
+                Token::Punctuator(String::from("<<=")),
+                Token::Literal(LiteralType::Int(2)),
+                Token::Punctuator(String::from(";")),
+            ],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_longest_punctuator_wins() {
+        let tokens = Tokenizer::from_string("... .. . << <= <<= >>=>".to_string()).unwrap();
+        let expected = ["...", ".", ".", ".", "<<", "<=", "<<=", ">>=", ">"];
+        assert_eq!(punctuators(tokens), expected)
+    }
+
+    #[test]
+    fn test_punctuator_table_is_configurable() {
+        let options = LexOptions {
+            punctuators: &["<", "<<", "=", "..."],
+            ..LexOptions::default()
+        };
+        let tokens = Tokenizer::with_options("<<= ... <".to_string(), options).unwrap();
+        assert_eq!(punctuators(tokens), ["<<", "=", "...", "<"]);
+
+        let error = Tokenizer::with_options("+".to_string(), options).unwrap_err();
+        let expected = LexError::UnexpectedCharacter {
+            character: '+',
+            span: Span::new(0, 1),
+        };
+        assert_eq!(error, expected)
+    }
+
+    #[test]
+    fn test_empty_punctuator_entries_are_ignored() {
+        let options = LexOptions {
+            punctuators: &["", "+"],
+            ..LexOptions::default()
+        };
+        let tokens = Tokenizer::with_options("1+2".to_string(), options).unwrap();
+        let expected = vec![
+            Token::Literal(LiteralType::Int(1)),
+            Token::Punctuator(String::from("+")),
+            Token::Literal(LiteralType::Int(2)),
+        ];
+        assert_eq!(tokens.tokens, expected);
+
+        let options = LexOptions {
+            punctuators: &[""],
+            ..LexOptions::default()
+        };
+        let error = Tokenizer::with_options("+-".to_string(), options).unwrap_err();
+        let expected = LexError::UnexpectedCharacter {
+            character: '+',
+            span: Span::new(0, 1),
+        };
+        assert_eq!(error, expected)
+    }
+
+    #[test]
+    fn test_tokenize_identifier_name() {
+        let tokens = Tokenizer::from_string("_a1 r éa".to_string());
+        let expected = LexError::UnexpectedCharacter {
+            character: 'é',
+            span: Span::new(6, 8),
+        };
+        assert_eq!(tokens.unwrap_err(), expected);
+
+        let tokens = Tokenizer::from_string("_a1 r".to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Identifier(IdentifierType::Name(String::from("_a1"))),
+                Token::Identifier(IdentifierType::Name(String::from("r"))),
+            ],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_number_followed_by_name() {
+        let error = Tokenizer::from_string("3abs(x)".to_string()).unwrap_err();
+        let expected = LexError::MissingOperator {
+            span: Span::new(1, 4),
+        };
+        assert_eq!(error, expected);
+
+        let error = Tokenizer::from_string("0x1fg_2".to_string()).unwrap_err();
+        let expected = LexError::MissingOperator {
+            span: Span::new(4, 7),
+        };
+        assert_eq!(error, expected);
+
+        let tokens = Tokenizer::from_string("3 * abs(x)".to_string()).unwrap();
+        assert_eq!(tokens.tokens.len(), 6)
+    }
+
+    #[test]
+    fn test_skip_to_statement_boundary() {
+        let mut lexer = Lexer::new("1 $ 2 \"a;b\" 3; 4 5\n6");
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_err());
+        lexer.skip_to_statement_boundary();
+        let expected = (Token::Literal(LiteralType::Int(4)), Span::new(15, 16));
+        assert_eq!(lexer.next(), Some(Ok(expected)));
+
+        lexer.skip_to_statement_boundary();
+        let expected = (Token::Literal(LiteralType::Int(6)), Span::new(19, 20));
+        assert_eq!(lexer.next(), Some(Ok(expected)));
+
+        lexer.skip_to_statement_boundary();
+        assert_eq!(lexer.next(), None);
+
+        let options = LexOptions {
+            no_tabs: true,
+            ..LexOptions::default()
+        };
+        let mut lexer = Lexer::with_options("1 $ 2\n\t3", options);
+        lexer.next();
+        lexer.next();
+        lexer.skip_to_statement_boundary();
+        let rest: Vec<_> = lexer.collect();
+        let expected = vec![
+            Err(LexError::TabInIndentation {
+                span: Span::new(6, 7),
+            }),
+            Ok((Token::Literal(LiteralType::Int(3)), Span::new(7, 8))),
+        ];
+        assert_eq!(rest, expected)
+    }
+
+    #[test]
+    fn test_comments_are_skipped() {
+        let tokens = Tokenizer::from_string("1 // 2\n3 /* 4\n */ 5/**/6".to_string()).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Literal(LiteralType::Int(1)),
+                Token::Literal(LiteralType::Int(3)),
+                Token::Literal(LiteralType::Int(5)),
+                Token::Literal(LiteralType::Int(6)),
+            ],
+        };
+        assert_eq!(tokens, expected);
+
+        let error = Tokenizer::from_string("1 /* 2 *".to_string()).unwrap_err();
+        let expected = LexError::UnterminatedComment {
+            span: Span::new(2, 8),
+        };
+        assert_eq!(error, expected)
+    }
+
+    #[test]
+    fn test_comments_disallowed() {
+        let options = LexOptions {
+            allow_comments: false,
+            ..LexOptions::default()
+        };
+        let tokens = Tokenizer::with_options("1 // 2 /* */".to_string(), options).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Literal(LiteralType::Int(1)),
+                Token::Punctuator(String::from("/")),
+                Token::Punctuator(String::from("/")),
+                Token::Literal(LiteralType::Int(2)),
+                Token::Punctuator(String::from("/")),
+                Token::Punctuator(String::from("*")),
+                Token::Punctuator(String::from("*")),
+                Token::Punctuator(String::from("/")),
+            ],
+        };
+        assert_eq!(tokens, expected)
+    }
+
+    #[test]
+    fn test_comments_in_declaration() {
+        let expected = Tokenizer {
+            tokens: vec![Token::Identifier(IdentifierType::Var(Identifier {
+                value: LiteralType::Int(0),
+                label: String::from("x"),
+            }))],
+        };
+        for source in ["int /* count */ x;", "int x /* c */;", "int// c\nx;"] {
+            let tokens = Tokenizer::from_string(source.to_string()).unwrap();
+            assert_eq!(tokens, expected, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_line_comment_continues_over_escaped_newline() {
+        let tokens = Tokenizer::from_string("// c \\\nstill comment".to_string()).unwrap();
+        assert_eq!(tokens.tokens, vec![]);
+
+        let tokens = Tokenizer::from_string("// c \\\r\nstill\n1".to_string()).unwrap();
+        assert_eq!(tokens.tokens, vec![Token::Literal(LiteralType::Int(1))]);
+    }
+
+    #[test]
+    fn test_tab_after_newline_in_block_comment() {
+        let options = LexOptions {
+            no_tabs: true,
+            ..LexOptions::default()
+        };
+        let error = Tokenizer::with_options("1 /*\n*/\t2".to_string(), options).unwrap_err();
+        let expected = LexError::TabInIndentation {
+            span: Span::new(7, 8),
+        };
+        assert_eq!(error, expected)
+    }
+
+    #[test]
+    fn test_tab_inside_declaration() {
+        let options = LexOptions {
+            no_tabs: true,
+            ..LexOptions::default()
+        };
+        for (source, label) in [("int\n\tx;", "x"), ("int x\n\t;", "x")] {
+            let tokens = Tokenizer::with_options(source.to_string(), options).unwrap();
+            let expected = vec![Token::Identifier(IdentifierType::Var(Identifier {
+                value: LiteralType::Int(0),
+                label: String::from(label),
+            }))];
+            assert_eq!(tokens.tokens, expected, "{:?}", source);
+        }
+
+        // The line break inside the declaration doesn't make the tab after
+        // it indentation.
+        let tokens = Tokenizer::with_options("int x\n; \t1".to_string(), options).unwrap();
+        assert_eq!(tokens.tokens.len(), 2)
+    }
+
+    #[test]
+    fn test_skip_to_statement_boundary_over_multiline_comment() {
+        let mut lexer = Lexer::new("1 $ /*\n*/ 2; 3");
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_err());
+        lexer.skip_to_statement_boundary();
+        let expected = (Token::Literal(LiteralType::Int(3)), Span::new(13, 14));
+        assert_eq!(lexer.next(), Some(Ok(expected)))
+    }
+
+    #[test]
+    fn test_hash_punctuators() {
+        let options = LexOptions {
+            allow_comments: false,
+            ..LexOptions::default()
+        };
+        let tokens = Tokenizer::with_options("# x".to_string(), options).unwrap();
+        let expected = Tokenizer {
+            tokens: vec![
+                Token::Punctuator(String::from("#")),
+                Token::Identifier(IdentifierType::Name(String::from("x"))),
+            ],
+        };
+        assert_eq!(tokens, expected);
+
+        let tokens = Tokenizer::from_string("a ## b".to_string()).unwrap();
+        assert_eq!(tokens.tokens[1], Token::Punctuator(String::from("##")));
+
+        for (source, hashes) in [("r#abc", "#"), ("r##x", "##"), ("r#1", "#")] {
+            let tokens = Tokenizer::with_options(source.to_string(), options).unwrap();
+            assert_eq!(
+                tokens.tokens[..2],
+                [
+                    Token::Identifier(IdentifierType::Name(String::from("r"))),
+                    Token::Punctuator(String::from(hashes)),
+                ],
+                "{:?}",
+                source
+            );
+            assert_eq!(tokens.tokens.len(), 3, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_skip_to_statement_boundary_after_error_before_semicolon() {
+        let mut lexer = Lexer::new("1 $; 2; 3");
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_err());
+        lexer.skip_to_statement_boundary();
+        let expected = (Token::Literal(LiteralType::Int(2)), Span::new(5, 6));
+        assert_eq!(lexer.next(), Some(Ok(expected)));
+
+        let mut lexer = Lexer::new("x = 3abs; y = 2;");
+        lexer.next();
+        lexer.next();
+        let error = LexError::MissingOperator {
+            span: Span::new(5, 8),
+        };
+        assert_eq!(lexer.next(), Some(Err(error)));
+        lexer.skip_to_statement_boundary();
+        let expected = (
+            Token::Identifier(IdentifierType::Name(String::from("y"))),
+            Span::new(10, 11),
+        );
+        assert_eq!(lexer.next(), Some(Ok(expected)));
+    }
+
+    /// Every Unicode scalar value, alone and in a few lexical contexts, must
+    /// lex to tokens or errors without panicking or looping forever.
+    /// Takes several seconds in a debug build, so it is left out of the
+    /// default run. Use `cargo test --release -- --ignored`.
+    #[test]
+    #[ignore = "slow; run in release with --ignored"]
+    fn test_lexing_is_total_over_unicode() {
+        let contexts = ["", "1", "0x", "int ", "\"", "\"\\", "r#"];
+        let mut source = String::new();
+        for c in char::MIN..=char::MAX {
+            for context in contexts {
+                source.clear();
+                source.push_str(context);
+                source.push(c);
+                source.push(c);
+                Lexer::new(&source).for_each(drop);
+            }
+        }
+    }
 }
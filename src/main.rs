@@ -1,5 +1,3 @@
-mod lexer;
-
 fn main() {
     println!("Hello, world!");
 }